@@ -1,17 +1,26 @@
 #![feature(test)]
 
 extern crate afsort;
+#[cfg(feature = "bench-compare")]
+extern crate radsort;
 extern crate rand;
 extern crate regex;
 extern crate test;
+#[cfg(feature = "bench-compare")]
+extern crate voracious_radix_sort;
 
-use afsort::AFSortable;
+use afsort::{AFSortable, DigitAt};
 use rand::Rng;
 use regex::Regex;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::mem;
 use std::path::PathBuf;
 use test::Bencher;
+#[cfg(feature = "bench-compare")]
+use voracious_radix_sort::RadixSort;
 
 #[bench]
 fn sort_en_strings_rand_10_000_std(b: &mut Bencher) {
@@ -28,12 +37,318 @@ fn sort_en_strings_rand_10_000_af(b: &mut Bencher) {
 #[bench]
 fn sort_en_strings_rand_100_000_std(b: &mut Bencher) {
     let strings = strings_en(&Regex::new(r".*").unwrap(), 100_000);
-    b.iter(|| strings.clone().sort_unstable())
+    let mut buf = strings.clone();
+    b.iter(|| {
+        //Reuses the allocations of the strings, which otherwise dominate the variance
+        buf.clone_from(&strings);
+        buf.sort_unstable()
+    })
 }
 
 #[bench]
 fn sort_en_strings_rand_100_000_af(b: &mut Bencher) {
     let strings = strings_en(&Regex::new(r".*").unwrap(), 100_000);
+    let mut buf = strings.clone();
+    b.iter(|| {
+        //Reuses the allocations of the strings, which otherwise dominate the variance
+        buf.clone_from(&strings);
+        buf.af_sort_unstable()
+    })
+}
+
+#[bench]
+fn sort_en_strings_rand_100_000_af_largest_first(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 100_000);
+    let mut buf = strings.clone();
+    let mut sorter = afsort::RadixSorter::new().with_largest_buckets_first(true);
+    b.iter(|| {
+        buf.clone_from(&strings);
+        sorter.sort(&mut buf)
+    })
+}
+
+#[bench]
+fn sort_en_strings_rand_100_000_af_sorter(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 100_000);
+    let mut buf = strings.clone();
+    let mut sorter = afsort::RadixSorter::new();
+    b.iter(|| {
+        buf.clone_from(&strings);
+        sorter.sort(&mut buf)
+    })
+}
+
+#[bench]
+fn sort_equal_strings_1_000_000_af(b: &mut Bencher) {
+    let strings = vec![String::from("the same string, a million times"); 1_000_000];
+    let mut buf = strings.clone();
+    b.iter(|| {
+        buf.clone_from(&strings);
+        buf.af_sort_unstable()
+    })
+}
+
+#[bench]
+fn sort_equal_strings_1_000_000_std(b: &mut Bencher) {
+    let strings = vec![String::from("the same string, a million times"); 1_000_000];
+    let mut buf = strings.clone();
+    b.iter(|| {
+        buf.clone_from(&strings);
+        buf.sort_unstable()
+    })
+}
+
+//The cost of copying the input, which the 100k sort benches include
+#[bench]
+fn clone_en_strings_rand_100_000(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 100_000);
+    let mut buf = strings.clone();
+    b.iter(|| buf.clone_from(&strings))
+}
+
+#[bench]
+fn sort_en_strings_rand_10_000_lowercase_std(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 10_000);
+    b.iter(|| strings.clone().sort_unstable_by_key(|s| s.to_lowercase()))
+}
+
+#[bench]
+fn sort_en_strings_rand_10_000_lowercase_af_by_digit(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 10_000);
+    b.iter(|| {
+        afsort::sort_unstable_by_digit(
+            &mut strings.clone(),
+            |s, digit| s.to_lowercase().as_bytes().get(digit).cloned(),
+            |remaining| remaining.sort_unstable_by_key(|s| s.to_lowercase()),
+        )
+    })
+}
+
+#[bench]
+fn sort_en_strings_rand_10_000_lowercase_af_extracted(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 10_000);
+    b.iter(|| afsort::sort_unstable_by_extracted(&mut strings.clone(), |s| s.to_lowercase()))
+}
+
+#[bench]
+fn sort_en_strings_presorted_100_000_af(b: &mut Bencher) {
+    let strings = strings_presorted(100_000);
+    let mut buf = strings.clone();
+    b.iter(|| {
+        buf.clone_from(&strings);
+        buf.af_sort_unstable()
+    })
+}
+
+#[bench]
+fn sort_en_strings_presorted_100_000_af_presorted(b: &mut Bencher) {
+    let strings = strings_presorted(100_000);
+    let mut buf = strings.clone();
+    b.iter(|| {
+        buf.clone_from(&strings);
+        afsort::sort_unstable_presorted(&mut buf)
+    })
+}
+
+#[bench]
+fn sort_en_strings_presorted_100_000_std(b: &mut Bencher) {
+    let strings = strings_presorted(100_000);
+    let mut buf = strings.clone();
+    b.iter(|| {
+        buf.clone_from(&strings);
+        buf.sort_unstable()
+    })
+}
+
+#[bench]
+fn sort_en_strings_rand_16_std(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 16);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_strings_rand_16_af(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 16);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_strings_rand_32_std(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 32);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_strings_rand_32_af(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 32);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_strings_rand_48_std(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 48);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_strings_rand_48_af(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 48);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_strings_rand_64_std(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 64);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_strings_rand_64_af(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 64);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_u32_16_std(b: &mut Bencher) {
+    let nums = rand_u32(16);
+    b.iter(|| nums.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_u32_16_af(b: &mut Bencher) {
+    let nums = rand_u32(16);
+    b.iter(|| nums.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_u32_32_std(b: &mut Bencher) {
+    let nums = rand_u32(32);
+    b.iter(|| nums.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_u32_32_af(b: &mut Bencher) {
+    let nums = rand_u32(32);
+    b.iter(|| nums.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_u32_48_std(b: &mut Bencher) {
+    let nums = rand_u32(48);
+    b.iter(|| nums.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_u32_48_af(b: &mut Bencher) {
+    let nums = rand_u32(48);
+    b.iter(|| nums.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_u32_64_std(b: &mut Bencher) {
+    let nums = rand_u32(64);
+    b.iter(|| nums.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_u32_64_af(b: &mut Bencher) {
+    let nums = rand_u32(64);
+    b.iter(|| nums.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_long_strings_40_std(b: &mut Bencher) {
+    let strings = long_strings_en(40);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_urls_40_std(b: &mut Bencher) {
+    let strings = urls_en(40);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_long_strings_40_af(b: &mut Bencher) {
+    let strings = long_strings_en(40);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_urls_40_af(b: &mut Bencher) {
+    let strings = urls_en(40);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_long_strings_64_std(b: &mut Bencher) {
+    let strings = long_strings_en(64);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_urls_64_std(b: &mut Bencher) {
+    let strings = urls_en(64);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_long_strings_64_af(b: &mut Bencher) {
+    let strings = long_strings_en(64);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_urls_64_af(b: &mut Bencher) {
+    let strings = urls_en(64);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_long_strings_128_std(b: &mut Bencher) {
+    let strings = long_strings_en(128);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_urls_128_std(b: &mut Bencher) {
+    let strings = urls_en(128);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_long_strings_128_af(b: &mut Bencher) {
+    let strings = long_strings_en(128);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_urls_128_af(b: &mut Bencher) {
+    let strings = urls_en(128);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_long_strings_256_std(b: &mut Bencher) {
+    let strings = long_strings_en(256);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_urls_256_std(b: &mut Bencher) {
+    let strings = urls_en(256);
+    b.iter(|| strings.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_en_long_strings_256_af(b: &mut Bencher) {
+    let strings = long_strings_en(256);
+    b.iter(|| strings.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_en_urls_256_af(b: &mut Bencher) {
+    let strings = urls_en(256);
     b.iter(|| strings.clone().af_sort_unstable())
 }
 
@@ -63,6 +378,205 @@ fn sort_en_strings_lower_10_000_af(b: &mut Bencher) {
     b.iter(|| strings.clone().af_sort_unstable())
 }
 
+#[bench]
+fn sort_en_strings_lower_10_000_length_bucketed(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r"^[a-z]+$").unwrap(), 10000);
+    b.iter(|| sort_unstable_length_bucketed(&mut strings.clone()))
+}
+
+#[bench]
+fn sort_en_strings_rand_100_000_length_bucketed(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 100_000);
+    b.iter(|| sort_unstable_length_bucketed(&mut strings.clone()))
+}
+
+#[bench]
+fn sort_en_strings_lower_10_000_two_byte(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r"^[a-z]+$").unwrap(), 10000);
+    b.iter(|| sort_unstable_by_two_byte_digits(&mut strings.clone(), |s| s))
+}
+
+#[bench]
+fn sort_en_strings_rand_10_000_two_byte(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 10_000);
+    b.iter(|| sort_unstable_by_two_byte_digits(&mut strings.clone(), |s| s))
+}
+
+#[bench]
+fn sort_en_strings_rand_100_000_two_byte(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 100_000);
+    b.iter(|| sort_unstable_by_two_byte_digits(&mut strings.clone(), |s| s))
+}
+
+#[bench]
+fn sort_mixed_length_bytes_10_000_std(b: &mut Bencher) {
+    let keys = mixed_length_bytes(10_000);
+    b.iter(|| keys.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_mixed_length_bytes_10_000_af(b: &mut Bencher) {
+    let keys = mixed_length_bytes(10_000);
+    b.iter(|| afsort::sort_unstable_by(&mut keys.clone(), |k| &k[..]))
+}
+
+#[bench]
+fn sort_mixed_length_bytes_10_000_af_unlimited_depth(b: &mut Bencher) {
+    let keys = mixed_length_bytes(10_000);
+    let mut sorter = afsort::RadixSorter::new().with_max_depth(usize::MAX);
+    b.iter(|| sorter.sort(&mut keys.clone()))
+}
+
+#[bench]
+fn sort_random_length_byte_slices_10_000_std(b: &mut Bencher) {
+    let keys = rand_length_bytes(10_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_random_length_byte_slices_10_000_af(b: &mut Bencher) {
+    let keys = rand_length_bytes(10_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_random_length_byte_slices_100_000_std(b: &mut Bencher) {
+    let keys = rand_length_bytes(100_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_random_length_byte_slices_100_000_af(b: &mut Bencher) {
+    let keys = rand_length_bytes(100_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_fixed_length_byte_slices_10_000_std(b: &mut Bencher) {
+    let keys = fixed_length_bytes(10_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_fixed_length_byte_slices_10_000_af(b: &mut Bencher) {
+    let keys = fixed_length_bytes(10_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_fixed_length_byte_slices_100_000_std(b: &mut Bencher) {
+    let keys = fixed_length_bytes(100_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_fixed_length_byte_slices_100_000_af(b: &mut Bencher) {
+    let keys = fixed_length_bytes(100_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_structured_byte_slices_10_000_std(b: &mut Bencher) {
+    let keys = structured_bytes(10_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_structured_byte_slices_10_000_af(b: &mut Bencher) {
+    let keys = structured_bytes(10_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_structured_byte_slices_100_000_std(b: &mut Bencher) {
+    let keys = structured_bytes(100_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_structured_byte_slices_100_000_af(b: &mut Bencher) {
+    let keys = structured_bytes(100_000);
+    let refs: Vec<&[u8]> = keys.iter().map(|k| &k[..]).collect();
+    b.iter(|| refs.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_shared_prefix_bytes_10_000_std(b: &mut Bencher) {
+    let keys = shared_prefix_bytes(10_000);
+    b.iter(|| keys.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_shared_prefix_bytes_10_000_af(b: &mut Bencher) {
+    let keys = shared_prefix_bytes(10_000);
+    b.iter(|| afsort::sort_unstable_by(&mut keys.clone(), |k| &k[..]))
+}
+
+//Without the distinguishing prefix estimate, which otherwise sorts all of these with the std sort
+#[bench]
+fn sort_shared_prefix_bytes_10_000_af_max_depth_64(b: &mut Bencher) {
+    let keys = shared_prefix_bytes(10_000);
+    let mut sorter = afsort::RadixSorter::new().with_key_length_ratio(usize::MAX);
+    b.iter(|| sorter.sort(&mut keys.clone()))
+}
+
+#[bench]
+fn sort_shared_prefix_bytes_10_000_af_unlimited_depth(b: &mut Bencher) {
+    let keys = shared_prefix_bytes(10_000);
+    let mut sorter = afsort::RadixSorter::new()
+        .with_key_length_ratio(usize::MAX)
+        .with_max_depth(usize::MAX);
+    b.iter(|| sorter.sort(&mut keys.clone()))
+}
+
+#[bench]
+fn sort_large_records_10_000_af(b: &mut Bencher) {
+    let records = large_records(10_000);
+    b.iter(|| records.clone().af_sort_unstable())
+}
+
+#[bench]
+fn sort_large_records_10_000_via_permutation(b: &mut Bencher) {
+    let records = large_records(10_000);
+    b.iter(|| records.clone().af_sort_unstable_via_permutation())
+}
+
+#[bench]
+fn sort_large_records_10_000_by(b: &mut Bencher) {
+    let records = large_records(10_000);
+    b.iter(|| afsort::sort_unstable_by(&mut records.clone(), |r| &r.key))
+}
+
+#[bench]
+fn sort_large_records_10_000_by_key_soa(b: &mut Bencher) {
+    let records = large_records(10_000);
+    b.iter(|| afsort::sort_unstable_by_key_soa(&mut records.clone(), |r| &r.key))
+}
+
+#[bench]
+fn argsort_en_strings_1_000_000_af(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 1_000_000);
+    b.iter(|| afsort::af_argsort_with_inverse(&strings, |s| s))
+}
+
+#[cfg(feature = "rayon")]
+#[bench]
+fn argsort_en_strings_1_000_000_af_par(b: &mut Bencher) {
+    let strings = strings_en(&Regex::new(r".*").unwrap(), 1_000_000);
+    b.iter(|| afsort::par_af_argsort_unstable_by(&strings, |s| s))
+}
+
 #[bench]
 fn sort_u8_1_000_std(b: &mut Bencher) {
     let nums = rand_u8(1_000);
@@ -75,6 +589,12 @@ fn sort_u8_1_000_af(b: &mut Bencher) {
     b.iter(|| nums.clone().af_sort_unstable())
 }
 
+#[bench]
+fn sort_u8_1_000_adaptive(b: &mut Bencher) {
+    let nums = rand_u8(1_000);
+    b.iter(|| afsort::sort_unstable_adaptive(&mut nums.clone()))
+}
+
 #[bench]
 fn sort_u16_1_000_000_std(b: &mut Bencher) {
     let nums = rand_u16(1_000_000);
@@ -99,6 +619,37 @@ fn sort_u32_1_000_000_af(b: &mut Bencher) {
     b.iter(|| nums.clone().af_sort_unstable())
 }
 
+#[bench]
+fn sort_chars_1_000_000_std(b: &mut Bencher) {
+    let chars = rand_bmp_chars(1_000_000);
+    b.iter(|| chars.clone().sort_unstable())
+}
+
+#[bench]
+fn sort_chars_1_000_000_af(b: &mut Bencher) {
+    let chars = rand_bmp_chars(1_000_000);
+    b.iter(|| afsort::sort_unstable_chars(&mut chars.clone()))
+}
+
+//All four bytes of the code points, to compare with the chars that only read the needed ones
+#[bench]
+fn sort_chars_1_000_000_af_four_bytes(b: &mut Bencher) {
+    let chars = rand_bmp_chars(1_000_000);
+    b.iter(|| {
+        afsort::sort_unstable_by_digit(
+            &mut chars.clone(),
+            |&c, digit| (c as u32).get_digit_at(digit),
+            |remaining| remaining.sort_unstable(),
+        )
+    })
+}
+
+#[bench]
+fn sort_u32_1_000_000_desc_af(b: &mut Bencher) {
+    let nums = rand_u32(1_000_000);
+    b.iter(|| afsort::sort_unstable_descending(&mut nums.clone()))
+}
+
 #[bench]
 fn sort_u64_1_000_000_std(b: &mut Bencher) {
     let nums = rand_u64(1_000_000);
@@ -111,6 +662,130 @@ fn sort_u64_1_000_000_af(b: &mut Bencher) {
     b.iter(|| nums.clone().af_sort_unstable())
 }
 
+#[bench]
+fn sort_u32_1_000_000_adaptive(b: &mut Bencher) {
+    let nums = rand_u32(1_000_000);
+    b.iter(|| afsort::sort_unstable_adaptive(&mut nums.clone()))
+}
+
+#[bench]
+fn sort_u64_1_000_000_adaptive(b: &mut Bencher) {
+    let nums = rand_u64(1_000_000);
+    b.iter(|| afsort::sort_unstable_adaptive(&mut nums.clone()))
+}
+
+#[bench]
+fn sort_u32_1_000_000_lsd(b: &mut Bencher) {
+    let nums = rand_u32(1_000_000);
+    b.iter(|| afsort::lsd_sort(&mut nums.clone()))
+}
+
+#[bench]
+fn sort_u64_1_000_000_lsd(b: &mut Bencher) {
+    let nums = rand_u64(1_000_000);
+    b.iter(|| afsort::lsd_sort(&mut nums.clone()))
+}
+
+#[cfg(feature = "bench-compare")]
+#[bench]
+fn sort_u8_1_000_radsort(b: &mut Bencher) {
+    let nums = rand_u8(1_000);
+    b.iter(|| radsort::sort(&mut nums.clone()))
+}
+
+#[cfg(feature = "bench-compare")]
+#[bench]
+fn sort_u8_1_000_voracious(b: &mut Bencher) {
+    let nums = rand_u8(1_000);
+    b.iter(|| nums.clone().voracious_sort())
+}
+
+#[cfg(feature = "bench-compare")]
+#[bench]
+fn sort_u16_1_000_000_radsort(b: &mut Bencher) {
+    let nums = rand_u16(1_000_000);
+    b.iter(|| radsort::sort(&mut nums.clone()))
+}
+
+#[cfg(feature = "bench-compare")]
+#[bench]
+fn sort_u16_1_000_000_voracious(b: &mut Bencher) {
+    let nums = rand_u16(1_000_000);
+    b.iter(|| nums.clone().voracious_sort())
+}
+
+#[cfg(feature = "bench-compare")]
+#[bench]
+fn sort_u32_1_000_000_radsort(b: &mut Bencher) {
+    let nums = rand_u32(1_000_000);
+    b.iter(|| radsort::sort(&mut nums.clone()))
+}
+
+#[cfg(feature = "bench-compare")]
+#[bench]
+fn sort_u32_1_000_000_voracious(b: &mut Bencher) {
+    let nums = rand_u32(1_000_000);
+    b.iter(|| nums.clone().voracious_sort())
+}
+
+#[cfg(feature = "bench-compare")]
+#[bench]
+fn sort_u64_1_000_000_radsort(b: &mut Bencher) {
+    let nums = rand_u64(1_000_000);
+    b.iter(|| radsort::sort(&mut nums.clone()))
+}
+
+#[cfg(feature = "bench-compare")]
+#[bench]
+fn sort_u64_1_000_000_voracious(b: &mut Bencher) {
+    let nums = rand_u64(1_000_000);
+    b.iter(|| nums.clone().voracious_sort())
+}
+
+#[derive(Clone)]
+struct LargeRecord {
+    key: String,
+    //Only there to make the record expensive to move
+    #[allow(dead_code)]
+    payload: [u64; 512],
+}
+
+impl DigitAt for LargeRecord {
+    fn get_digit_at(&self, digit: usize) -> Option<u8> {
+        self.key.get_digit_at(digit)
+    }
+}
+
+impl PartialEq for LargeRecord {
+    fn eq(&self, other: &LargeRecord) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for LargeRecord {}
+
+impl PartialOrd for LargeRecord {
+    fn partial_cmp(&self, other: &LargeRecord) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LargeRecord {
+    fn cmp(&self, other: &LargeRecord) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+fn large_records(n: usize) -> Vec<LargeRecord> {
+    strings_en(&Regex::new(r".*").unwrap(), n)
+        .into_iter()
+        .map(|key| LargeRecord {
+            key,
+            payload: [0; 512],
+        })
+        .collect()
+}
+
 fn rand_u8(n: usize) -> Vec<u8> {
     let mut rng = rand::thread_rng();
     let mut v = Vec::with_capacity(n);
@@ -129,6 +804,14 @@ fn rand_u16(n: usize) -> Vec<u16> {
     v
 }
 
+//Random chars of the Basic Multilingual Plane, below the surrogates
+fn rand_bmp_chars(n: usize) -> Vec<char> {
+    let mut rng = rand::thread_rng();
+    (0..n)
+        .map(|_| std::char::from_u32(rng.gen_range(0, 0xD800)).unwrap())
+        .collect()
+}
+
 fn rand_u32(n: usize) -> Vec<u32> {
     let mut rng = rand::thread_rng();
     let mut v = Vec::with_capacity(n);
@@ -147,6 +830,264 @@ fn rand_u64(n: usize) -> Vec<u64> {
     v
 }
 
+//Byte strings of 1 to 16 random bytes, and one in ten of 1 to 10 kB, which share their first kB
+fn mixed_length_bytes(n: usize) -> Vec<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    let header: Vec<u8> = (0..1024).map(|_| rng.gen()).collect();
+    (0..n)
+        .map(|i| {
+            if i % 10 == 0 {
+                let len = rng.gen_range(1024, 10 * 1024);
+                let mut key = header.clone();
+                key.extend((1024..len).map(|_| rng.gen::<u8>()));
+                key
+            } else {
+                (0..rng.gen_range(1, 17)).map(|_| rng.gen()).collect()
+            }
+        })
+        .collect()
+}
+
+//Random byte strings of 1 to 32 bytes
+//Sorts by first partitioning by length, then sorting each group of equal length and finally
+//merging the groups. Within a group, no key ends early. Kept here, rather than in the crate,
+//since it needs a second buffer for the merge and was measured to be slower than
+//af_sort_unstable().
+fn sort_unstable_length_bucketed<T>(vec: &mut [T])
+where
+    T: AsRef<[u8]> + Default,
+{
+    afsort::sort_unstable_by_digit(
+        vec,
+        |elem, digit| (elem.as_ref().len() as u64).get_digit_at(digit),
+        |remaining| remaining.sort_unstable_by_key(|elem| elem.as_ref().len()),
+    );
+    let mut runs = Vec::new();
+    let mut start = 0;
+    while start < vec.len() {
+        let len = vec[start].as_ref().len();
+        let mut end = start + 1;
+        while end < vec.len() && vec[end].as_ref().len() == len {
+            end += 1;
+        }
+        afsort::sort_unstable_by_digit(
+            &mut vec[start..end],
+            |elem, digit| elem.as_ref().get(digit).cloned(),
+            |remaining| remaining.sort_unstable_by(|e1, e2| e1.as_ref().cmp(e2.as_ref())),
+        );
+        runs.push((start, end));
+        start = end;
+    }
+    let mut taken: Vec<T> = vec.iter_mut().map(mem::take).collect();
+    let mut order = Vec::with_capacity(taken.len());
+    let mut heads = BinaryHeap::with_capacity(runs.len());
+    for (start, end) in runs {
+        heads.push(Reverse((taken[start].as_ref(), start, end)));
+    }
+    while let Some(Reverse((_, start, end))) = heads.pop() {
+        order.push(start);
+        if start + 1 < end {
+            heads.push(Reverse((taken[start + 1].as_ref(), start + 1, end)));
+        }
+    }
+    for (dst, src) in vec.iter_mut().zip(order) {
+        *dst = mem::take(&mut taken[src]);
+    }
+}
+
+//Like afsort::sort_unstable_by, but reads two bytes of the key per level instead of one. This
+//halves the depth of the recursion, at the cost of up to 257 times as many buckets per level.
+//Slices with fewer elements than buckets continue with one byte per level. Kept here, rather than
+//in the crate, since it was measured to be about as fast as af_sort_unstable(), not faster.
+fn sort_unstable_by_two_byte_digits<T, S>(vec: &mut [T], sort_by: S)
+where
+    S: Fn(&T) -> &str,
+{
+    sort_req_two_byte(vec, &sort_by, 0, &mut Vec::new());
+}
+
+//Number of values of a digit of two bytes per value of the first byte. The last byte of an odd
+//length key has no second byte, and sorts before all second bytes.
+const TWO_BYTE_RADIX: u32 = 257;
+
+//Gets the two bytes at depth..depth + 2 as a single digit. A key with only one byte left gets a
+//digit that sorts before all digits with the same first byte, and is divisible by the radix.
+#[inline]
+fn two_byte_digit(key: &[u8], depth: usize) -> Option<u32> {
+    match (key.get(depth), key.get(depth + 1)) {
+        (Some(&first), Some(&second)) => Some(first as u32 * TWO_BYTE_RADIX + 1 + second as u32),
+        (Some(&first), None) => Some(first as u32 * TWO_BYTE_RADIX),
+        _ => None,
+    }
+}
+
+//The counts, offsets and next free slot per bucket of each level are pushed onto scratch
+fn sort_req_two_byte<T, S>(vec: &mut [T], sort_by: &S, mut depth: usize, scratch: &mut Vec<usize>)
+where
+    S: Fn(&T) -> &str,
+{
+    let (min, max) = loop {
+        if vec.len() <= afsort::DEFAULT_FALLBACK_THRESHOLD {
+            //All keys share the bytes before depth
+            vec.sort_unstable_by(|e1, e2| {
+                sort_by(e1).as_bytes()[depth..].cmp(&sort_by(e2).as_bytes()[depth..])
+            });
+            return;
+        }
+        let mut min = u32::MAX;
+        let mut max = 0u32;
+        let mut any_none = false;
+        for elem in vec.iter() {
+            match two_byte_digit(sort_by(elem).as_bytes(), depth) {
+                Some(v) => {
+                    min = min.min(v);
+                    max = max.max(v);
+                }
+                None => any_none = true,
+            }
+        }
+        //No item had a value for this depth
+        if min == u32::MAX {
+            return;
+        }
+        if min == max && !any_none {
+            //All keys end with the same last byte, and are thus equal
+            if min % TWO_BYTE_RADIX == 0 {
+                return;
+            }
+            depth += 2;
+            continue;
+        }
+        break (min, max);
+    };
+    // +2 instead of +1 for special 0 bucket
+    let num_items = (max - min + 2) as usize;
+    //Going through more buckets than there are elements costs more than it saves, so continue
+    //with one byte per level
+    if num_items > vec.len() {
+        afsort::sort_unstable_by_digit(
+            vec,
+            |elem, digit| sort_by(elem).as_bytes().get(depth + digit).cloned(),
+            |remaining| {
+                remaining.sort_unstable_by(|e1, e2| {
+                    sort_by(e1).as_bytes()[depth..].cmp(&sort_by(e2).as_bytes()[depth..])
+                })
+            },
+        );
+        return;
+    }
+    let base = scratch.len();
+    scratch.resize(base + 3 * num_items, 0);
+    {
+        let (counts, rest) = scratch[base..].split_at_mut(num_items);
+        let (offsets, next_free) = rest.split_at_mut(num_items);
+        let bucket = |elem: &T| match two_byte_digit(sort_by(elem).as_bytes(), depth) {
+            Some(v) => (v + 1 - min) as usize,
+            None => 0,
+        };
+        for elem in vec.iter() {
+            counts[bucket(elem)] += 1;
+        }
+        let mut sum = 0usize;
+        for i in 0..counts.len() {
+            offsets[i] = sum;
+            sum += counts[i];
+        }
+        next_free.copy_from_slice(offsets);
+        for block in 0..num_items - 1 {
+            let end = offsets[block + 1];
+            while next_free[block] < end {
+                let i = next_free[block];
+                let radix_val = bucket(&vec[i]);
+                vec.swap(i, next_free[radix_val]);
+                next_free[radix_val] += 1;
+            }
+        }
+    }
+    //Recurse into all buckets, except the ones where the keys have ended, since those are all
+    //equal
+    let offsets = base + num_items;
+    for i in 1..num_items {
+        if (i as u32 - 1 + min) % TWO_BYTE_RADIX == 0 {
+            continue;
+        }
+        let start = scratch[offsets + i];
+        let end = if i + 1 < num_items {
+            scratch[offsets + i + 1]
+        } else {
+            vec.len()
+        };
+        if end - start > 1 {
+            sort_req_two_byte(&mut vec[start..end], sort_by, depth + 2, scratch);
+        }
+    }
+    scratch.truncate(base);
+}
+
+fn rand_length_bytes(n: usize) -> Vec<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    (0..n)
+        .map(|_| (0..rng.gen_range(1, 33)).map(|_| rng.gen()).collect())
+        .collect()
+}
+
+//Random byte strings of 16 bytes, like hashes or UUIDs
+fn fixed_length_bytes(n: usize) -> Vec<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    (0..n)
+        .map(|_| (0..16).map(|_| rng.gen()).collect())
+        .collect()
+}
+
+//Keys of a big-endian tenant id out of 16, followed by a big-endian timestamp and a random suffix,
+//like the keys of a time series store
+fn structured_bytes(n: usize) -> Vec<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    (0..n)
+        .map(|_| {
+            let mut key = rng.gen_range(0u32, 16).to_be_bytes().to_vec();
+            key.extend_from_slice(&rng.gen_range(1_600_000_000u64, 1_700_000_000).to_be_bytes());
+            key.extend((0..4).map(|_| rng.gen::<u8>()));
+            key
+        })
+        .collect()
+}
+
+//Byte strings that all share their first 4 kB, followed by 8 random bytes
+fn shared_prefix_bytes(n: usize) -> Vec<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    let prefix: Vec<u8> = (0..4096).map(|_| rng.gen()).collect();
+    (0..n)
+        .map(|_| {
+            let mut key = prefix.clone();
+            key.extend((0..8).map(|_| rng.gen::<u8>()));
+            key
+        })
+        .collect()
+}
+
+//Strings of five random words, with an average length of about 50 bytes
+fn long_strings_en(n: usize) -> Vec<String> {
+    let words = strings_en(&Regex::new(r".*").unwrap(), 5 * n);
+    words.chunks(5).map(|c| c.join(" ")).collect()
+}
+
+//Strings with a long shared prefix, like URLs of a single site
+fn urls_en(n: usize) -> Vec<String> {
+    strings_en(&Regex::new(r".*").unwrap(), n)
+        .into_iter()
+        .map(|w| format!("https://www.example.com/words/{}", w))
+        .collect()
+}
+
+//Sorted strings, followed by a random tail of 5% of them
+fn strings_presorted(n: usize) -> Vec<String> {
+    let mut strings = strings_en(&Regex::new(r".*").unwrap(), n);
+    let tail = n / 20;
+    strings[..n - tail].sort_unstable();
+    strings
+}
+
 fn strings_en(re: &Regex, n: usize) -> Vec<String> {
     let d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     let f = File::open(d.join("test_resources/american-english.txt")).unwrap();